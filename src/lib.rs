@@ -3,7 +3,282 @@ extern crate test;
 
 extern crate rand;
 
-use rand::Rng;
+#[cfg(feature = "serde1")]
+extern crate serde;
+#[cfg(feature = "serde1")]
+#[macro_use]
+extern crate serde_derive;
+
+use std::cell::Cell;
+
+use rand::{Rng, RngCore};
+use rand::distributions::Distribution;
+
+/// Fixed-point scale used to represent the `[0, 1)` domain when sampling from a rational
+/// probability: `low`/`high` are integers counting units of `1 / SCALE`, so comparisons against
+/// `numerator / denominator` can be done with exact (if widened) integer arithmetic instead of
+/// `f64`, which can't represent most rationals exactly and drifts after repeated rescaling.
+const SCALE: u128 = 1 << 64;
+
+/// Advances the buffered random bit stream by one bit, refilling `bits` from `rng` (and counting
+/// the refill in `words`) a whole `u64` at a time so that repeated single-bit draws don't each
+/// cost a full word of entropy.
+#[inline]
+fn next_bit<R: Rng + ?Sized>(rng: &mut R, bits: &mut u64, shift: &mut u8, words: &mut u64) -> bool {
+    if *shift == 0 {
+        *bits = rng.next_u64();
+        *shift = 64;
+        *words += 1;
+    }
+    let bit = (*bits & 1) == 1;
+    *bits >>= 1;
+    *shift -= 1;
+    bit
+}
+
+/// Splits `[low, high)` at its midpoint without the rounding bias a plain `(low + high) / 2`
+/// would introduce when `high - low` is odd: the leftover unit alternates which side it's added
+/// to via `carry`, so it doesn't always favour `low` (or always `high`) over a long run of calls.
+#[inline]
+fn halve_with_carry(low: u64, high: u64, carry: &mut bool) -> u64 {
+    let range = high - low;
+    let mut mid = low + (range >> 1);
+    if range & 1 == 1 && *carry {
+        mid += 1;
+    }
+    *carry = !*carry;
+    mid
+}
+
+/// The probability threshold a sampler compares its interval state against.
+///
+/// `Float` is what the original implementation used directly: simple, but `f64` can only
+/// represent a probability like `1/3` approximately, and the repeated `p.recip()` rescaling in
+/// the hot loop accumulates rounding error over many iterations. `Ratio` instead keeps the
+/// probability as an exact `numerator / denominator` and is paired with an `Interval::Ratio`,
+/// whose bounds are compared against it using widened integer arithmetic.
+#[derive(Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde1", derive(Serialize, Deserialize))]
+enum Threshold {
+    Float(f64),
+    Ratio { num: u64, den: u64 },
+}
+
+/// The `[low, high)` interval state of the arithmetic-decoder style sampler. The variant always
+/// matches the `Threshold` it's paired with.
+///
+/// This, together with the buffered `bits`/`shift`, is the part of a sampler's state that
+/// `DecoderState` snapshots: it's everything needed to resume drawing from the same point in the
+/// random bit stream later.
+#[derive(Clone, Copy)]
+#[cfg_attr(feature = "serde1", derive(Serialize, Deserialize))]
+enum Interval {
+    Float { low: f64, high: f64 },
+    Ratio { low: u64, high: u64, carry: bool },
+}
+
+impl Interval {
+    fn for_threshold(threshold: &Threshold) -> Interval {
+        match *threshold {
+            Threshold::Float(_) => Interval::Float { low: 0.0, high: 1.0 },
+            Threshold::Ratio { .. } => {
+                Interval::Ratio { low: 0, high: u64::MAX, carry: false }
+            }
+        }
+    }
+}
+
+impl Threshold {
+    /// Validates `probability` as a `[0, 1]` success probability and wraps it as a `Float`
+    /// threshold.
+    fn from_probability(probability: f64) -> Result<Threshold, Error> {
+        if !probability.is_finite() || probability.is_sign_negative() || probability > 1.0 {
+            return Err(Error::InvalidProbability);
+        }
+        Ok(Threshold::Float(probability))
+    }
+
+    /// Validates `numerator / denominator` as an exact success probability and wraps it as a
+    /// `Ratio` threshold.
+    fn from_ratio(numerator: u64, denominator: u64) -> Result<Threshold, Error> {
+        if denominator == 0 {
+            return Err(Error::InvalidRatio);
+        }
+        if numerator > denominator {
+            return Err(Error::InvalidProbability);
+        }
+        Ok(Threshold::Ratio { num: numerator, den: denominator })
+    }
+}
+
+/// Identifies which sampler type produced a `DecoderState`.
+///
+/// `BernoulliRng` and `BinomialRng` both pair their `Interval` with a `Threshold` the same way, so
+/// a state saved from one is safe to resume on the other; `Bernoulli` does too, just through a
+/// `Cell` instead of owning the `Rng`. `CategoricalRng` also happens to use `Interval::Float`, but
+/// its bounds are CDF bucket boundaries, not a probability threshold, so its states must never be
+/// confused with the other three's even though `Interval`'s own `Float`/`Ratio` discriminant can't
+/// tell them apart.
+#[derive(Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde1", derive(Serialize, Deserialize))]
+enum SamplerKind {
+    Threshold,
+    Categorical,
+}
+
+/// Identifies *which* `Threshold` or CDF a `DecoderState` was saved from, so `restore` can reject
+/// a state saved against a different probability/ratio or weight vector even when its `kind` and
+/// `Interval` shape happen to match. A `CategoricalRng`'s CDF is a `Vec<f64>`, too big to store
+/// verbatim in a `Copy` snapshot, so it's folded into a `u64` fingerprint instead; a `Threshold` is
+/// tiny and `Copy`, so it's kept as-is.
+#[derive(Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde1", derive(Serialize, Deserialize))]
+enum Fingerprint {
+    Threshold(Threshold),
+    Cdf(u64),
+}
+
+impl Fingerprint {
+    /// Folds a CDF's bits into a single `u64` via FNV-1a, so it can ride along in a `Copy`
+    /// `DecoderState` without cloning the whole `Vec<f64>`.
+    fn of_cdf(cdf: &[f64]) -> Fingerprint {
+        let mut hash = 0xcbf29ce484222325u64;
+        for x in cdf {
+            hash = (hash ^ x.to_bits()).wrapping_mul(0x100000001b3);
+        }
+        Fingerprint::Cdf(hash)
+    }
+}
+
+/// A snapshot of a sampler's arithmetic-decoder state: the current `[low, high)` interval plus
+/// the buffered bits leftover from the last `rng.next_u64()` draw and the running count of words
+/// drawn so far. Saving and restoring this lets a partially-consumed bit stream be serialized
+/// and resumed later (e.g. across process restarts, or to compare real bit consumption against
+/// the naive one-word-per-sample approach without losing the in-progress interval).
+#[derive(Clone, Copy)]
+#[cfg_attr(feature = "serde1", derive(Serialize, Deserialize))]
+pub struct DecoderState {
+    kind: SamplerKind,
+    fingerprint: Fingerprint,
+    interval: Interval,
+    bits: u64,
+    shift: u8,
+    words: u64,
+}
+
+/// The arithmetic-decoder bookkeeping every sampler needs: the current `[low, high)` `Interval`,
+/// the bits buffered from the last `rng.next_u64()` draw, and the running count of words drawn so
+/// far. `BernoulliRng`, `BinomialRng`, `Bernoulli`, and `CategoricalRng` each hold one of these
+/// instead of re-deriving its accounting and save/restore logic.
+#[derive(Clone, Copy)]
+struct Decoder {
+    interval: Interval,
+    bits: u64,
+    shift: u8,
+    words: u64,
+}
+
+impl Decoder {
+    fn new(interval: Interval) -> Decoder {
+        Decoder { interval, bits: 0, shift: 0, words: 0 }
+    }
+
+    /// The number of `rng.next_u64()` words actually drawn so far.
+    fn consumed_words(&self) -> u64 {
+        self.words
+    }
+
+    /// The number of random bits actually drawn so far, i.e. `consumed_words() * 64` minus
+    /// whatever's left over in the buffer unused.
+    fn consumed_bits(&self) -> u64 {
+        self.words * 64 - self.shift as u64
+    }
+
+    /// Snapshots this state under `kind`/`fingerprint`, so sampling can later be resumed from
+    /// exactly this point in the random bit stream via `restore`.
+    fn save(&self, kind: SamplerKind, fingerprint: Fingerprint) -> DecoderState {
+        DecoderState { kind, fingerprint, interval: self.interval, bits: self.bits, shift: self.shift, words: self.words }
+    }
+
+    /// Restores state previously captured with `save`.
+    ///
+    /// Fails with `Error::MismatchedDecoderState` if `state` wasn't saved from a sampler of the
+    /// same `kind` and `fingerprint` — e.g. a `CategoricalRng`'s bucket boundaries, a
+    /// threshold-based sampler using the other `Float`/`Ratio` representation, or simply a
+    /// different probability/ratio/weight vector than this sampler was built with.
+    fn restore(&mut self, state: DecoderState, kind: SamplerKind, fingerprint: Fingerprint) -> Result<(), Error> {
+        // `kind`/`fingerprint` already guarantee a matching `Interval` variant for any
+        // `DecoderState` produced by `save`; the discriminant check below is a defense-in-depth
+        // sanity check against a `DecoderState` reconstructed by hand or deserialized (via
+        // `serde1`) from corrupted or malicious bytes, where the fields could otherwise disagree.
+        if state.kind != kind
+            || state.fingerprint != fingerprint
+            || ::std::mem::discriminant(&self.interval) != ::std::mem::discriminant(&state.interval)
+        {
+            return Err(Error::MismatchedDecoderState);
+        }
+        self.interval = state.interval;
+        self.bits = state.bits;
+        self.shift = state.shift;
+        self.words = state.words;
+        Ok(())
+    }
+}
+
+/// Consumes random bits (via `next_bit`) until the current interval lies entirely on one side of
+/// `threshold`, returning that side as a bit, then rescales the interval relative to the side it
+/// landed on so the leftover precision can be reused by the next call.
+fn sample_bit<R: Rng + ?Sized>(threshold: &Threshold, decoder: &mut Decoder, rng: &mut R) -> bool {
+    let Decoder { ref mut interval, ref mut bits, ref mut shift, ref mut words } = *decoder;
+    match (*threshold, interval) {
+        (Threshold::Float(p), &mut Interval::Float { ref mut low, ref mut high }) => {
+            let p_recip = p.recip();
+            let pinv_recip = (1.0 - p).recip();
+            loop {
+                if *high < p {
+                    *low *= p_recip;
+                    *high *= p_recip;
+                    return true;
+                } else if *low > p {
+                    *low = (*low - p) * pinv_recip;
+                    *high = (*high - p) * pinv_recip;
+                    return false;
+                } else {
+                    let mid = 0.5 * (*low + *high);
+                    if next_bit(rng, bits, shift, words) {
+                        *low = mid;
+                    } else {
+                        *high = mid;
+                    }
+                }
+            }
+        }
+        (Threshold::Ratio { num, den }, &mut Interval::Ratio { ref mut low, ref mut high, ref mut carry }) => {
+            let num = num as u128;
+            let den = den as u128;
+            loop {
+                if (*high as u128) * den < num * SCALE {
+                    *low = ((*low as u128) * den / num) as u64;
+                    *high = ((*high as u128) * den / num) as u64;
+                    return true;
+                } else if (*low as u128) * den > num * SCALE {
+                    let den_minus_num = den - num;
+                    *low = (((*low as u128) * den - num * SCALE) / den_minus_num) as u64;
+                    *high = (((*high as u128) * den - num * SCALE) / den_minus_num) as u64;
+                    return false;
+                } else {
+                    let mid = halve_with_carry(*low, *high, carry);
+                    if next_bit(rng, bits, shift, words) {
+                        *low = mid;
+                    } else {
+                        *high = mid;
+                    }
+                }
+            }
+        }
+        _ => unreachable!("a Threshold is always paired with the matching Interval variant"),
+    }
+}
 
 /// The Bernoulli distribution
 ///
@@ -17,106 +292,351 @@ use rand::Rng;
 /// distribution keeps the random samples from the underlying Rng between calls to generate a
 /// random number.
 pub struct BernoulliRng<R> {
-    p: f64,
-    low: f64,
-    high: f64,
+    threshold: Threshold,
+    decoder: Decoder,
     rng: R,
-    shift: u8,
-    bits: u64
 }
 
 impl<R: Rng> BernoulliRng<R> {
     pub fn new(rng: R, probability: f64) -> Result<BernoulliRng<R>, Error> {
-        if probability.is_sign_negative() || probability > 1.0 {
-            return Err(Error::InvalidProbability);
-        }
-        Ok(BernoulliRng {
-            p: probability,
-            low: 0.0,
-            high: 1.0,
-            rng: rng,
-            shift: 0,
-            bits: 0
-        })
+        let threshold = Threshold::from_probability(probability)?;
+        Ok(BernoulliRng { decoder: Decoder::new(Interval::for_threshold(&threshold)), threshold, rng })
+    }
+
+    /// Like `new`, but takes the success probability as an exact `numerator / denominator`
+    /// instead of a `f64`, so probabilities that aren't exactly representable in binary floating
+    /// point (`1/3`, `1/10`, ...) are sampled without rounding drift.
+    pub fn from_ratio(rng: R, numerator: u64, denominator: u64) -> Result<BernoulliRng<R>, Error> {
+        let threshold = Threshold::from_ratio(numerator, denominator)?;
+        Ok(BernoulliRng { decoder: Decoder::new(Interval::for_threshold(&threshold)), threshold, rng })
+    }
+
+    /// See `Decoder::consumed_words`.
+    pub fn consumed_words(&self) -> u64 {
+        self.decoder.consumed_words()
+    }
+
+    /// See `Decoder::consumed_bits`.
+    pub fn consumed_bits(&self) -> u64 {
+        self.decoder.consumed_bits()
+    }
+
+    /// See `Decoder::save`.
+    pub fn save_state(&self) -> DecoderState {
+        self.decoder.save(SamplerKind::Threshold, Fingerprint::Threshold(self.threshold))
+    }
+
+    /// See `Decoder::restore`.
+    pub fn restore_state(&mut self, state: DecoderState) -> Result<(), Error> {
+        self.decoder.restore(state, SamplerKind::Threshold, Fingerprint::Threshold(self.threshold))
+    }
+}
+
+/// The Binomial distribution: the number of successes out of `n` independent Bernoulli(`p`)
+/// trials.
+///
+/// Since Bernoulli is the `n = 1` special case of Binomial, this drives the same arithmetic
+/// decoder `n` times and sums the resulting bits, reusing one buffered `bits`/`shift` stream
+/// (and one `[low, high)` interval) across all `n` trials instead of
+/// constructing `n` separate `BernoulliRng`s and paying a fresh word of entropy per trial.
+pub struct BinomialRng<R> {
+    threshold: Threshold,
+    decoder: Decoder,
+    rng: R,
+    n: u64,
+}
+
+impl<R: Rng> BinomialRng<R> {
+    pub fn new(rng: R, n: u64, probability: f64) -> Result<BinomialRng<R>, Error> {
+        let threshold = Threshold::from_probability(probability)?;
+        Ok(BinomialRng { decoder: Decoder::new(Interval::for_threshold(&threshold)), threshold, rng, n })
+    }
+
+    /// Like `new`, but takes the per-trial success probability as an exact
+    /// `numerator / denominator` instead of a `f64`.
+    pub fn from_ratio(rng: R, n: u64, numerator: u64, denominator: u64) -> Result<BinomialRng<R>, Error> {
+        let threshold = Threshold::from_ratio(numerator, denominator)?;
+        Ok(BinomialRng { decoder: Decoder::new(Interval::for_threshold(&threshold)), threshold, rng, n })
     }
 
-    #[inline]
-    fn next_bit(&mut self) -> bool {
-        if self.shift == 0 {
-            self.bits = self.rng.next_u64();
-            self.shift = 64;
+    /// Runs the `n` underlying Bernoulli trials and returns the number of successes.
+    pub fn next_u64(&mut self) -> u64 {
+        let mut successes = 0;
+        for _ in 0..self.n {
+            if sample_bit(&self.threshold, &mut self.decoder, &mut self.rng) {
+                successes += 1;
+            }
         }
-        let bit = (self.bits & 1) == 1;
-        self.bits >>= 1;
-        self.shift -= 1;
-        bit
+        successes
+    }
+
+    /// See `Decoder::consumed_words`.
+    pub fn consumed_words(&self) -> u64 {
+        self.decoder.consumed_words()
+    }
+
+    /// See `Decoder::consumed_bits`.
+    pub fn consumed_bits(&self) -> u64 {
+        self.decoder.consumed_bits()
+    }
+
+    /// See `Decoder::save`.
+    pub fn save_state(&self) -> DecoderState {
+        self.decoder.save(SamplerKind::Threshold, Fingerprint::Threshold(self.threshold))
+    }
+
+    /// See `Decoder::restore`.
+    pub fn restore_state(&mut self, state: DecoderState) -> Result<(), Error> {
+        self.decoder.restore(state, SamplerKind::Threshold, Fingerprint::Threshold(self.threshold))
+    }
+}
+
+/// The Bernoulli distribution, as a `rand::distributions::Distribution<bool>`.
+///
+/// Unlike `BernoulliRng`, this type does not own the underlying Rng: each call to `sample`
+/// borrows `&mut R` for the duration of the call, so it fits into rand's usual
+/// `d.sample(&mut rng)` / `rng.sample(d)` distribution API. The entropy-saving interval state
+/// that `BernoulliRng` keeps on itself is kept here too, behind a `Cell`, so that sampling
+/// through the borrowed-RNG form is exactly as bit-efficient as `BernoulliRng`.
+pub struct Bernoulli {
+    threshold: Threshold,
+    decoder: Cell<Decoder>,
+}
+
+impl Bernoulli {
+    pub fn new(probability: f64) -> Result<Bernoulli, Error> {
+        let threshold = Threshold::from_probability(probability)?;
+        Ok(Bernoulli { decoder: Cell::new(Decoder::new(Interval::for_threshold(&threshold))), threshold })
+    }
+
+    /// Like `new`, but takes the success probability as an exact `numerator / denominator`
+    /// instead of a `f64`, so probabilities that aren't exactly representable in binary floating
+    /// point (`1/3`, `1/10`, ...) are sampled without rounding drift.
+    pub fn from_ratio(numerator: u64, denominator: u64) -> Result<Bernoulli, Error> {
+        let threshold = Threshold::from_ratio(numerator, denominator)?;
+        Ok(Bernoulli { decoder: Cell::new(Decoder::new(Interval::for_threshold(&threshold))), threshold })
+    }
+
+    /// See `Decoder::consumed_words`.
+    pub fn consumed_words(&self) -> u64 {
+        self.decoder.get().consumed_words()
+    }
+
+    /// See `Decoder::consumed_bits`.
+    pub fn consumed_bits(&self) -> u64 {
+        self.decoder.get().consumed_bits()
+    }
+
+    /// See `Decoder::save`.
+    pub fn save_state(&self) -> DecoderState {
+        self.decoder.get().save(SamplerKind::Threshold, Fingerprint::Threshold(self.threshold))
+    }
+
+    /// See `Decoder::restore`.
+    pub fn restore_state(&self, state: DecoderState) -> Result<(), Error> {
+        let mut decoder = self.decoder.get();
+        decoder.restore(state, SamplerKind::Threshold, Fingerprint::Threshold(self.threshold))?;
+        self.decoder.set(decoder);
+        Ok(())
+    }
+}
+
+impl Distribution<bool> for Bernoulli {
+    fn sample<R: Rng + ?Sized>(&self, rng: &mut R) -> bool {
+        let mut decoder = self.decoder.get();
+        let result = sample_bit(&self.threshold, &mut decoder, rng);
+        self.decoder.set(decoder);
+        result
     }
 }
 
 pub enum Error {
-    InvalidProbability
+    InvalidProbability,
+    InvalidRatio,
+    InvalidWeights,
+    MismatchedDecoderState,
 }
 
 impl ::std::error::Error for Error {
     fn description(&self) -> &str {
-        match self {
-            &Error::InvalidProbability => "invalid probability specified",
+        match *self {
+            Error::InvalidProbability => "invalid probability specified",
+            Error::InvalidRatio => "invalid ratio specified: denominator must be non-zero",
+            Error::InvalidWeights => "invalid weights specified: must be non-empty, finite, non-negative, and sum to more than zero",
+            Error::MismatchedDecoderState => "decoder state was not saved from a sampler of the same kind and probability/ratio/weights",
         }
     }
 }
 
 impl ::std::fmt::Display for Error {
     fn fmt(&self, f: &mut ::std::fmt::Formatter) -> ::std::fmt::Result {
-        match self {
-            &Error::InvalidProbability => write!(f, "invalid probability specified")
+        match *self {
+            Error::InvalidProbability => write!(f, "invalid probability specified"),
+            Error::InvalidRatio => write!(f, "invalid ratio specified"),
+            Error::InvalidWeights => write!(f, "invalid weights specified"),
+            Error::MismatchedDecoderState => write!(f, "mismatched decoder state"),
         }
     }
 }
 
 impl ::std::fmt::Debug for Error {
     fn fmt(&self, f: &mut ::std::fmt::Formatter) -> ::std::fmt::Result {
-        match self {
-            &Error::InvalidProbability => write!(f, "invalid probability specified")
+        match *self {
+            Error::InvalidProbability => write!(f, "invalid probability specified"),
+            Error::InvalidRatio => write!(f, "invalid ratio specified"),
+            Error::InvalidWeights => write!(f, "invalid weights specified"),
+            Error::MismatchedDecoderState => write!(f, "mismatched decoder state"),
         }
     }
 }
 
-impl<R: Rng> Rng for BernoulliRng<R> {
+// rand 0.5 made `Rng` a blanket-implemented marker trait over `RngCore` (it no longer declares
+// `next_u32` itself), so producing random numbers means implementing `RngCore`; `Rng` then comes
+// for free via that blanket impl.
+impl<R: Rng> RngCore for BernoulliRng<R> {
     fn next_u32(&mut self) -> u32 {
-        let (mut ret, mut i, mut high, mut low) = (0, 0, self.high, self.low);
-        let (p, p_recip, pinv_recip) = (self.p, self.p.recip(), (1.0 - self.p).recip());
-        while i != 32 {
-            if high < p {
-                ret = ret << 1 | 1;
-                i += 1;
-                low *= p_recip;
-                high *= p_recip;
-            } else if low > p {
-                ret = ret << 1;
-                i += 1;
-                low = (low - p) * pinv_recip;
-                high = (high - p) * pinv_recip;
+        let mut ret = 0u32;
+        for _ in 0..32 {
+            let bit = sample_bit(&self.threshold, &mut self.decoder, &mut self.rng);
+            ret = ret << 1 | (bit as u32);
+        }
+        ret
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        let hi = self.next_u32() as u64;
+        let lo = self.next_u32() as u64;
+        (hi << 32) | lo
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        let mut chunks = dest.chunks_exact_mut(4);
+        for chunk in &mut chunks {
+            chunk.copy_from_slice(&self.next_u32().to_le_bytes());
+        }
+        let remainder = chunks.into_remainder();
+        if !remainder.is_empty() {
+            let bytes = self.next_u32().to_le_bytes();
+            remainder.copy_from_slice(&bytes[..remainder.len()]);
+        }
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand::Error> {
+        self.fill_bytes(dest);
+        Ok(())
+    }
+}
+
+/// Finds the bucket `i` such that `cdf[i] <= x < cdf[i + 1]`, assuming `cdf` is sorted and
+/// `cdf[0] == 0.0`, `cdf[cdf.len() - 1] == 1.0`. Falls back to the last bucket for `x` at or past
+/// the final boundary, which is where the initial (unnarrowed) `high == 1.0` lands.
+#[inline]
+fn bucket_of(cdf: &[f64], x: f64) -> usize {
+    for i in 0..cdf.len() - 1 {
+        if x < cdf[i + 1] {
+            return i;
+        }
+    }
+    cdf.len() - 2
+}
+
+/// A categorical distribution over `0..weights.len()`, sampled with the same entropy-efficient
+/// arithmetic-decoding technique as `BernoulliRng`: the outcome is produced as soon as the current
+/// `[low, high)` interval collapses entirely within one of the distribution's CDF buckets, only
+/// consuming further `next_bit`s while it straddles a bucket boundary. For `k` uniformly weighted
+/// outcomes this draws close to `log2(k)` bits per sample rather than a fixed word.
+///
+/// Like `BernoulliRng`, this owns its underlying Rng and keeps interval state between calls, and
+/// shares the same `Interval`/`DecoderState` representation so its progress can be snapshotted
+/// and resumed the same way.
+pub struct CategoricalRng<R> {
+    cdf: Vec<f64>,
+    decoder: Decoder,
+    rng: R,
+}
+
+impl<R: Rng> CategoricalRng<R> {
+    pub fn new(rng: R, weights: &[f64]) -> Result<CategoricalRng<R>, Error> {
+        if weights.is_empty() {
+            return Err(Error::InvalidWeights);
+        }
+        let mut total = 0.0;
+        for &w in weights {
+            if w.is_sign_negative() || !w.is_finite() {
+                return Err(Error::InvalidWeights);
+            }
+            total += w;
+        }
+        if total <= 0.0 {
+            return Err(Error::InvalidWeights);
+        }
+        let mut cdf = Vec::with_capacity(weights.len() + 1);
+        cdf.push(0.0);
+        let mut acc = 0.0;
+        for &w in weights {
+            acc += w / total;
+            cdf.push(acc);
+        }
+        *cdf.last_mut().unwrap() = 1.0;
+        Ok(CategoricalRng { cdf, decoder: Decoder::new(Interval::Float { low: 0.0, high: 1.0 }), rng })
+    }
+
+    /// Draws the index of one of `0..weights.len()` outcomes.
+    pub fn next_u32(&mut self) -> u32 {
+        let (low, high) = match self.decoder.interval {
+            Interval::Float { low, high } => (low, high),
+            Interval::Ratio { .. } => unreachable!("CategoricalRng always uses Interval::Float"),
+        };
+        let mut low = low;
+        let mut high = high;
+        loop {
+            let low_bucket = bucket_of(&self.cdf, low);
+            let high_bucket = bucket_of(&self.cdf, high);
+            if low_bucket == high_bucket || high == self.cdf[low_bucket + 1] {
+                let (blow, bhigh) = (self.cdf[low_bucket], self.cdf[low_bucket + 1]);
+                let range_recip = (bhigh - blow).recip();
+                low = (low - blow) * range_recip;
+                high = (high - blow) * range_recip;
+                self.decoder.interval = Interval::Float { low, high };
+                return low_bucket as u32;
             } else {
                 let mid = 0.5 * (low + high);
-                if self.next_bit() {
+                if next_bit(&mut self.rng, &mut self.decoder.bits, &mut self.decoder.shift, &mut self.decoder.words) {
                     low = mid;
                 } else {
                     high = mid;
                 }
             }
         }
-        self.high = high;
-        self.low = low;
-        ret
+    }
+
+    /// See `Decoder::consumed_words`.
+    pub fn consumed_words(&self) -> u64 {
+        self.decoder.consumed_words()
+    }
+
+    /// See `Decoder::consumed_bits`.
+    pub fn consumed_bits(&self) -> u64 {
+        self.decoder.consumed_bits()
+    }
+
+    /// See `Decoder::save`.
+    pub fn save_state(&self) -> DecoderState {
+        self.decoder.save(SamplerKind::Categorical, Fingerprint::of_cdf(&self.cdf))
+    }
+
+    /// See `Decoder::restore`.
+    pub fn restore_state(&mut self, state: DecoderState) -> Result<(), Error> {
+        self.decoder.restore(state, SamplerKind::Categorical, Fingerprint::of_cdf(&self.cdf))
     }
 }
 
 #[test]
 fn it_works() {
     let mut v = 0;
-    let mut rng = rand::thread_rng();
+    let rng = rand::thread_rng();
     let mut distr = if let Ok(v) = BernoulliRng::new(rng, 0.75) { v } else { panic!() };
-    for i in 0..10000 {
+    for _ in 0..10000 {
         let o = distr.next_u32();
         v += o.count_ones();
     }
@@ -124,12 +644,289 @@ fn it_works() {
 
 }
 
+#[test]
+fn distribution_matches_rng() {
+    let mut rng = rand::thread_rng();
+    let distr = if let Ok(v) = Bernoulli::new(0.75) { v } else { panic!() };
+    let mut v = 0;
+    for _ in 0..10000 {
+        if distr.sample(&mut rng) {
+            v += 1;
+        }
+    }
+    let expected = 7500.0;
+    println!("{}/10000", v);
+    assert!((v as f64 - expected).abs() < expected * 0.05);
+}
+
+#[test]
+fn from_ratio_one_third() {
+    let rng = rand::thread_rng();
+    let mut distr = if let Ok(v) = BernoulliRng::from_ratio(rng, 1, 3) { v } else { panic!() };
+    let mut v = 0;
+    for _ in 0..30000 {
+        let o = distr.next_u32();
+        v += o.count_ones();
+    }
+    let total = 30000 * 32;
+    let expected = total as f64 / 3.0;
+    println!("{}/{}", v, total);
+    assert!((v as f64 - expected).abs() < expected * 0.05);
+}
+
+#[test]
+fn from_ratio_rejects_zero_denominator() {
+    let rng = rand::thread_rng();
+    match BernoulliRng::from_ratio(rng, 1, 0) {
+        Err(Error::InvalidRatio) => {}
+        _ => panic!("expected Error::InvalidRatio"),
+    }
+}
+
+#[test]
+fn from_ratio_rejects_numerator_greater_than_denominator() {
+    let rng = rand::thread_rng();
+    match BernoulliRng::from_ratio(rng, 2, 1) {
+        Err(Error::InvalidProbability) => {}
+        _ => panic!("expected Error::InvalidProbability"),
+    }
+}
+
+#[test]
+fn new_rejects_nan_probability() {
+    let rng = rand::thread_rng();
+    match BernoulliRng::new(rng, f64::NAN) {
+        Err(Error::InvalidProbability) => {}
+        _ => panic!("expected Error::InvalidProbability"),
+    }
+}
+
+#[test]
+fn categorical_distributes_by_weight() {
+    let rng = rand::thread_rng();
+    let mut distr = if let Ok(v) = CategoricalRng::new(rng, &[1.0, 2.0, 1.0]) { v } else { panic!() };
+    let mut counts = [0u32; 3];
+    for _ in 0..30000 {
+        counts[distr.next_u32() as usize] += 1;
+    }
+    println!("{:?}/30000", counts);
+    let expected = [7500.0, 15000.0, 7500.0];
+    for i in 0..3 {
+        assert!((counts[i] as f64 - expected[i]).abs() < expected[i] * 0.1);
+    }
+}
+
+#[test]
+fn new_rejects_empty_weights() {
+    let rng = rand::thread_rng();
+    match CategoricalRng::new(rng, &[]) {
+        Err(Error::InvalidWeights) => {}
+        _ => panic!("expected Error::InvalidWeights"),
+    }
+}
+
+#[test]
+fn new_rejects_negative_weight() {
+    let rng = rand::thread_rng();
+    match CategoricalRng::new(rng, &[1.0, -1.0]) {
+        Err(Error::InvalidWeights) => {}
+        _ => panic!("expected Error::InvalidWeights"),
+    }
+}
+
+#[test]
+fn new_rejects_non_finite_weight() {
+    let rng = rand::thread_rng();
+    match CategoricalRng::new(rng, &[1.0, f64::NAN]) {
+        Err(Error::InvalidWeights) => {}
+        _ => panic!("expected Error::InvalidWeights"),
+    }
+}
+
+#[test]
+fn new_rejects_zero_total_weight() {
+    let rng = rand::thread_rng();
+    match CategoricalRng::new(rng, &[0.0, 0.0]) {
+        Err(Error::InvalidWeights) => {}
+        _ => panic!("expected Error::InvalidWeights"),
+    }
+}
+
+#[test]
+fn binomial_counts_successes() {
+    let rng = rand::thread_rng();
+    let mut distr = if let Ok(v) = BinomialRng::new(rng, 100, 0.75) { v } else { panic!() };
+    let mut total = 0;
+    for _ in 0..1000 {
+        total += distr.next_u64();
+    }
+    let trials = 1000 * 100;
+    let expected = trials as f64 * 0.75;
+    println!("{}/{}", total, trials);
+    assert!((total as f64 - expected).abs() < expected * 0.05);
+}
+
+#[test]
+fn binomial_from_ratio_rejects_zero_denominator() {
+    let rng = rand::thread_rng();
+    match BinomialRng::from_ratio(rng, 100, 1, 0) {
+        Err(Error::InvalidRatio) => {}
+        _ => panic!("expected Error::InvalidRatio"),
+    }
+}
+
+#[test]
+fn binomial_from_ratio_rejects_numerator_greater_than_denominator() {
+    let rng = rand::thread_rng();
+    match BinomialRng::from_ratio(rng, 100, 2, 1) {
+        Err(Error::InvalidProbability) => {}
+        _ => panic!("expected Error::InvalidProbability"),
+    }
+}
+
+#[test]
+fn consumed_bits_is_never_more_than_naive() {
+    let rng = rand::thread_rng();
+    let mut distr = if let Ok(v) = BernoulliRng::new(rng, 0.75) { v } else { panic!() };
+    for _ in 0..10000 {
+        distr.next_u32();
+    }
+    // Each `next_u32` call is 32 Bernoulli samples; a naive implementation would draw one u64
+    // (64 bits) per sample, and this checks the much looser bound of one u64 per call.
+    assert!(distr.consumed_bits() < 10000 * 64);
+}
+
+#[test]
+fn restore_state_resets_interval_and_accounting() {
+    let rng = rand::thread_rng();
+    let mut distr = if let Ok(v) = BernoulliRng::from_ratio(rng, 1, 3) { v } else { panic!() };
+    for _ in 0..100 {
+        distr.next_u32();
+    }
+    let saved = distr.save_state();
+    let words_at_save = distr.consumed_words();
+
+    for _ in 0..100 {
+        distr.next_u32();
+    }
+    distr.restore_state(saved).unwrap();
+    assert_eq!(distr.consumed_words(), words_at_save);
+    match (distr.decoder.interval, saved.interval) {
+        (Interval::Ratio { low, high, carry }, Interval::Ratio { low: l, high: h, carry: c }) => {
+            assert_eq!((low, high, carry), (l, h, c));
+        }
+        _ => panic!("expected Interval::Ratio"),
+    }
+}
+
+#[test]
+fn restore_state_rejects_mismatched_threshold_kind() {
+    let rng = rand::thread_rng();
+    let mut float_distr = if let Ok(v) = BernoulliRng::new(rng, 0.75) { v } else { panic!() };
+    let rng = rand::thread_rng();
+    let ratio_distr = if let Ok(v) = BernoulliRng::from_ratio(rng, 1, 3) { v } else { panic!() };
+    let ratio_state = ratio_distr.save_state();
+    match float_distr.restore_state(ratio_state) {
+        Err(Error::MismatchedDecoderState) => {}
+        _ => panic!("expected Error::MismatchedDecoderState"),
+    }
+}
+
+#[test]
+fn restore_state_rejects_categorical_state_on_bernoulli() {
+    let rng = rand::thread_rng();
+    let categorical_distr = if let Ok(v) = CategoricalRng::new(rng, &[1.0, 2.0, 1.0]) { v } else { panic!() };
+    let categorical_state = categorical_distr.save_state();
+
+    let rng = rand::thread_rng();
+    let mut float_distr = if let Ok(v) = BernoulliRng::new(rng, 0.75) { v } else { panic!() };
+    // Both use Interval::Float, so the Interval discriminant can't tell a categorical sampler's
+    // bucket boundaries apart from a threshold sampler's; the `SamplerKind` tag and fingerprint do.
+    match float_distr.restore_state(categorical_state) {
+        Err(Error::MismatchedDecoderState) => {}
+        _ => panic!("expected Error::MismatchedDecoderState"),
+    }
+}
+
+#[test]
+fn restore_state_rejects_mismatched_probability() {
+    let rng = rand::thread_rng();
+    let mut distr = if let Ok(v) = BernoulliRng::new(rng, 0.75) { v } else { panic!() };
+    for _ in 0..50 {
+        distr.next_u32();
+    }
+    let saved = distr.save_state();
+
+    let rng = rand::thread_rng();
+    let mut other_distr = if let Ok(v) = BernoulliRng::new(rng, 0.1) { v } else { panic!() };
+    match other_distr.restore_state(saved) {
+        Err(Error::MismatchedDecoderState) => {}
+        _ => panic!("expected Error::MismatchedDecoderState"),
+    }
+}
+
+#[test]
+fn restore_state_rejects_mismatched_weights() {
+    let rng = rand::thread_rng();
+    let mut distr = if let Ok(v) = CategoricalRng::new(rng, &[1.0, 2.0, 1.0]) { v } else { panic!() };
+    for _ in 0..50 {
+        distr.next_u32();
+    }
+    let saved = distr.save_state();
+
+    let rng = rand::thread_rng();
+    let mut other_distr = if let Ok(v) = CategoricalRng::new(rng, &[1.0, 1.0]) { v } else { panic!() };
+    match other_distr.restore_state(saved) {
+        Err(Error::MismatchedDecoderState) => {}
+        _ => panic!("expected Error::MismatchedDecoderState"),
+    }
+}
+
+#[test]
+fn categorical_save_and_restore_state() {
+    let rng = rand::thread_rng();
+    let mut distr = if let Ok(v) = CategoricalRng::new(rng, &[1.0, 2.0, 1.0]) { v } else { panic!() };
+    for _ in 0..100 {
+        distr.next_u32();
+    }
+    let saved = distr.save_state();
+    let words_at_save = distr.consumed_words();
+
+    for _ in 0..100 {
+        distr.next_u32();
+    }
+    distr.restore_state(saved).unwrap();
+    assert_eq!(distr.consumed_words(), words_at_save);
+}
+
+#[bench]
+fn binomial_fast(b: &mut test::Bencher) {
+    let rng = rand::thread_rng();
+    let mut distr = if let Ok(v) = BinomialRng::new(rng, 100, 0.75) { v } else { panic!() };
+    b.iter(||{
+        for _ in 0..100 {
+            distr.next_u64();
+        }
+    });
+}
+
 #[bench]
 fn it_works_fast(b: &mut test::Bencher) {
-    let mut rng = rand::thread_rng();
+    let rng = rand::thread_rng();
     let mut distr = if let Ok(v) = BernoulliRng::new(rng, 0.75) { v } else { panic!() };
     b.iter(||{
-        for i in 0..10000 {
+        for _ in 0..10000 {
+            distr.next_u32();
+        }
+    });
+}
+
+#[bench]
+fn it_works_ratio(b: &mut test::Bencher) {
+    let rng = rand::thread_rng();
+    let mut distr = if let Ok(v) = BernoulliRng::from_ratio(rng, 3, 4) { v } else { panic!() };
+    b.iter(||{
+        for _ in 0..10000 {
             distr.next_u32();
         }
     });
@@ -139,7 +936,7 @@ fn it_works_fast(b: &mut test::Bencher) {
 fn it_works_faster(b: &mut test::Bencher) {
     let mut rng = rand::thread_rng();
     b.iter(||{
-        for i in 0..10000 {
+        for _ in 0..10000 {
             rng.next_u32();
         }
     });